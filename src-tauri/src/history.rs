@@ -0,0 +1,168 @@
+//! Bounded in-memory record of images the handler has optimized, so users can
+//! see cumulative savings and undo a pass that was too aggressive.
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::optimizer::{hash_rgba, ImageSnapshot, OptimizeReport};
+
+/// How many past optimizations to keep around.
+const MAX_ENTRIES: usize = 50;
+
+/// One optimized image, keyed by the content hash of its original bytes.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub original: ImageSnapshot,
+    pub optimized: ImageSnapshot,
+    pub timestamp_secs: u64,
+    pub bytes_saved: i64,
+}
+
+/// A [`HistoryEntry`] stripped of pixel data, for listing over Tauri IPC
+/// without shipping full-resolution RGBA buffers the frontend never renders.
+/// `restore_original` looks the full entry back up by `id` when it actually
+/// needs the pixels.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySummary {
+    pub id: u64,
+    pub original_width: usize,
+    pub original_height: usize,
+    pub optimized_width: usize,
+    pub optimized_height: usize,
+    pub timestamp_secs: u64,
+    pub bytes_saved: i64,
+}
+
+impl From<&HistoryEntry> for HistorySummary {
+    fn from(entry: &HistoryEntry) -> Self {
+        Self {
+            id: entry.id,
+            original_width: entry.original.width,
+            original_height: entry.original.height,
+            optimized_width: entry.optimized.width,
+            optimized_height: entry.optimized.height,
+            timestamp_secs: entry.timestamp_secs,
+            bytes_saved: entry.bytes_saved,
+        }
+    }
+}
+
+/// A bounded FIFO of [`HistoryEntry`] values, oldest dropped first.
+#[derive(Default)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    /// Records an optimization pass, keyed by the hash of the original image.
+    pub fn record(
+        &mut self,
+        original: ImageSnapshot,
+        optimized: ImageSnapshot,
+        report: OptimizeReport,
+    ) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry {
+            id: hash_rgba(&original.rgba),
+            original,
+            optimized,
+            timestamp_secs: now_secs(),
+            bytes_saved: report.bytes_saved(),
+        });
+    }
+
+    /// Lists entries without their RGBA buffers; see [`HistorySummary`].
+    pub fn list(&self) -> Vec<HistorySummary> {
+        self.entries.iter().map(HistorySummary::from).collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1x1 snapshot whose single byte makes it hash (and so id) uniquely.
+    fn snapshot(byte: u8) -> ImageSnapshot {
+        ImageSnapshot {
+            width: 1,
+            height: 1,
+            rgba: vec![byte, byte, byte, 255],
+        }
+    }
+
+    fn report(bytes_saved: i64) -> OptimizeReport {
+        // original_size/optimized_size are only used via bytes_saved() here.
+        OptimizeReport {
+            original_size: bytes_saved.max(0) as usize + 100,
+            optimized_size: 100,
+        }
+    }
+
+    #[test]
+    fn get_returns_none_for_an_unknown_id() {
+        let mut history = History::default();
+        history.record(snapshot(1), snapshot(2), report(10));
+
+        assert!(history.get(12345).is_none());
+    }
+
+    #[test]
+    fn get_finds_a_recorded_entry_by_its_original_content_hash() {
+        let mut history = History::default();
+        let original = snapshot(7);
+        let id = hash_rgba(&original.rgba);
+        history.record(original, snapshot(8), report(10));
+
+        let entry = history.get(id).expect("entry should be recorded under its hash");
+        assert_eq!(entry.id, id);
+    }
+
+    #[test]
+    fn clear_empties_the_history() {
+        let mut history = History::default();
+        history.record(snapshot(1), snapshot(2), report(10));
+        history.record(snapshot(3), snapshot(4), report(20));
+
+        history.clear();
+
+        assert!(history.list().is_empty());
+        assert!(history.get(hash_rgba(&snapshot(1).rgba)).is_none());
+    }
+
+    #[test]
+    fn recording_past_max_entries_evicts_the_oldest_first() {
+        let mut history = History::default();
+        // One past MAX_ENTRIES: the very first entry (byte 0) should be the
+        // one that gets evicted, since eviction is oldest-first.
+        for byte in 0..=MAX_ENTRIES as u16 {
+            history.record(snapshot(byte as u8), snapshot(byte as u8), report(10));
+        }
+
+        let entries = history.list();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert!(history.get(hash_rgba(&snapshot(0).rgba)).is_none());
+        assert!(history
+            .get(hash_rgba(&snapshot(MAX_ENTRIES as u8).rgba))
+            .is_some());
+    }
+}
@@ -1,12 +1,109 @@
+mod history;
+mod optimizer;
+mod settings;
+
 use clipboard_master::{CallbackResult, ClipboardHandler, Master};
+use clipboard_rs::ClipboardContext;
+use history::{History, HistorySummary};
+use optimizer::{optimize_clipboard_image, should_optimize, OptimizeOutcome, OptimizerConfig};
 
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Config shared between the clipboard-watching thread and the Tauri commands
+/// the frontend uses to change it.
+type SharedConfig = Arc<Mutex<OptimizerConfig>>;
+
+/// Whether the clipboard-watching thread is paused (leaving images alone),
+/// shared with the `pause_optimizer`/`resume_optimizer` commands.
+type SharedPaused = Arc<AtomicBool>;
+
+/// Hash of the last image we wrote to the clipboard, shared so both the
+/// watcher thread and `restore_original` can mark a write as self-initiated.
+type SharedLastHash = Arc<Mutex<Option<u64>>>;
 
-struct Handler;
+/// Record of past optimizations, shared with the history Tauri commands.
+type SharedHistory = Arc<Mutex<History>>;
+
+/// Watches the clipboard and re-encodes images that land on it.
+struct Handler {
+    config: SharedConfig,
+    paused: SharedPaused,
+    last_written_hash: SharedLastHash,
+    history: SharedHistory,
+}
+
+impl Handler {
+    fn new(
+        config: SharedConfig,
+        paused: SharedPaused,
+        last_written_hash: SharedLastHash,
+        history: SharedHistory,
+    ) -> Self {
+        Self {
+            config,
+            paused,
+            last_written_hash,
+            history,
+        }
+    }
+}
 
 impl ClipboardHandler for Handler {
     fn on_clipboard_change(&mut self) -> CallbackResult {
-        log::info!("Clipboard change happened!");
+        if self.paused.load(Ordering::Relaxed) {
+            return CallbackResult::Next;
+        }
+
+        // Opened once and threaded through both calls below, so the format
+        // check and the image read see the same clipboard content rather
+        // than racing two independently opened native handles.
+        let ctx = match ClipboardContext::new() {
+            Ok(ctx) => ctx,
+            Err(error) => {
+                log::warn!("Couldn't open the clipboard: {error}");
+                return CallbackResult::Next;
+            }
+        };
+
+        if !should_optimize(&ctx) {
+            return CallbackResult::Next;
+        }
+
+        let config = *self.config.lock().unwrap();
+        let skip_if_hash = *self.last_written_hash.lock().unwrap();
+        match optimize_clipboard_image(&ctx, config, skip_if_hash) {
+            Ok(OptimizeOutcome::SelfWrite) => {
+                log::debug!("Ignoring clipboard change we caused ourselves");
+            }
+            Ok(OptimizeOutcome::NotSmaller { report }) => {
+                log::info!(
+                    "Clipboard image already optimal ({} bytes, re-encode was {} bytes)",
+                    report.original_size,
+                    report.optimized_size
+                );
+            }
+            Ok(OptimizeOutcome::Optimized {
+                report,
+                original,
+                optimized,
+                written_hash,
+            }) => {
+                log::info!(
+                    "Optimized clipboard image: {} -> {} bytes ({} saved)",
+                    report.original_size,
+                    report.optimized_size,
+                    report.bytes_saved()
+                );
+                *self.last_written_hash.lock().unwrap() = Some(written_hash);
+                self.history
+                    .lock()
+                    .unwrap()
+                    .record(original, optimized, report);
+            }
+            Err(error) => log::warn!("Skipped clipboard image optimization: {error}"),
+        }
         CallbackResult::Next
     }
 
@@ -16,27 +113,108 @@ impl ClipboardHandler for Handler {
     }
 }
 
-// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+/// Returns the active optimization profile.
+#[tauri::command]
+fn get_settings(config: tauri::State<SharedConfig>) -> OptimizerConfig {
+    *config.lock().unwrap()
+}
+
+/// Replaces the active optimization profile and persists it to disk.
+#[tauri::command]
+fn set_settings(
+    profile: OptimizerConfig,
+    config: tauri::State<SharedConfig>,
+) -> Result<(), String> {
+    let profile = profile.sanitized();
+    settings::save(&profile)?;
+    *config.lock().unwrap() = profile;
+    Ok(())
+}
+
+/// Stops the handler from touching the clipboard until `resume_optimizer` is called.
+#[tauri::command]
+fn pause_optimizer(paused: tauri::State<SharedPaused>) {
+    paused.store(true, Ordering::Relaxed);
+}
+
+/// Resumes clipboard image optimization after `pause_optimizer`.
+#[tauri::command]
+fn resume_optimizer(paused: tauri::State<SharedPaused>) {
+    paused.store(false, Ordering::Relaxed);
+}
+
+/// Lists past optimizations, most recent first. Pixel data is omitted; use
+/// `restore_original` to act on a specific entry's image.
 #[tauri::command]
-fn greet(name: &str) -> String {
-    log::info!("234");
-    format!("Hello, {}! You've been greeted from Rust!", name)
+fn list_history(history: tauri::State<SharedHistory>) -> Vec<HistorySummary> {
+    let mut entries = history.lock().unwrap().list();
+    entries.reverse();
+    entries
+}
+
+/// Writes a history entry's original, unoptimized image back to the clipboard.
+#[tauri::command]
+fn restore_original(
+    id: u64,
+    history: tauri::State<SharedHistory>,
+    last_written_hash: tauri::State<SharedLastHash>,
+) -> Result<(), String> {
+    let original = history
+        .lock()
+        .unwrap()
+        .get(id)
+        .map(|entry| entry.original.clone())
+        .ok_or_else(|| format!("no history entry with id {id}"))?;
+
+    let written_hash = optimizer::write_to_clipboard(&original)?;
+    *last_written_hash.lock().unwrap() = Some(written_hash);
+    Ok(())
+}
+
+/// Clears the optimization history.
+#[tauri::command]
+fn clear_history(history: tauri::State<SharedHistory>) {
+    history.lock().unwrap().clear();
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    std::thread::spawn(|| {
-        let mut master = Master::new(Handler).expect("Failed to create clipboard master");
+    let config: SharedConfig = Arc::new(Mutex::new(settings::load()));
+    let paused: SharedPaused = Arc::new(AtomicBool::new(false));
+    let last_written_hash: SharedLastHash = Arc::new(Mutex::new(None));
+    let history: SharedHistory = Arc::new(Mutex::new(History::default()));
+
+    let handler_config = config.clone();
+    let handler_paused = paused.clone();
+    let handler_last_written_hash = last_written_hash.clone();
+    let handler_history = history.clone();
+    std::thread::spawn(move || {
+        let mut master = Master::new(Handler::new(
+            handler_config,
+            handler_paused,
+            handler_last_written_hash,
+            handler_history,
+        ))
+        .expect("Failed to create clipboard master");
         master.run().expect("Failed to run clipboard master");
     });
 
     tauri::Builder::default()
-        .plugin(
-            tauri_plugin_log::Builder::new()
-                .build(),
-        )
+        .manage(config)
+        .manage(paused)
+        .manage(last_written_hash)
+        .manage(history)
+        .plugin(tauri_plugin_log::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![greet])
+        .invoke_handler(tauri::generate_handler![
+            get_settings,
+            set_settings,
+            pause_optimizer,
+            resume_optimizer,
+            list_history,
+            restore_original,
+            clear_history
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,420 @@
+//! Re-encodes the image currently sitting on the clipboard to shrink its size.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+
+use clipboard_rs::{Clipboard as _, ClipboardContext, ContentFormat, RustImage, RustImageData};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{DynamicImage, ImageEncoder, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// Whether the clipboard currently holds content the handler should optimize.
+///
+/// The clipboard can hold several representations of the same copy at once
+/// (plain text, HTML, RTF, image, file list). If HTML or RTF is present
+/// alongside the image, a rich editor paste will use that representation
+/// instead of the raw image, likely with the original image embedded in it
+/// — rewriting just the image format would desync the two, so we leave
+/// clipboards like that alone entirely.
+///
+/// Takes the already-opened `ctx` the caller is about to read the image from
+/// rather than opening its own, so the format check and the image read see
+/// the same clipboard content instead of racing a second native handle open
+/// against whatever changed the clipboard next.
+pub fn should_optimize(ctx: &ClipboardContext) -> bool {
+    ctx.has(ContentFormat::Image) && !ctx.has(ContentFormat::Html) && !ctx.has(ContentFormat::Rtf)
+}
+
+/// Default re-encode quality used when the caller doesn't have a configured
+/// profile yet (0 = smallest/slowest, 100 = largest/fastest).
+pub const DEFAULT_QUALITY: u8 = 80;
+
+/// Output codec to re-encode clipboard images as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Conversion {
+    /// Re-encode as PNG, keeping it lossless.
+    Keep,
+    WebP,
+    Avif,
+    Jpeg,
+}
+
+/// Codec, quality and size limits the handler re-encodes clipboard images with.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct OptimizerConfig {
+    pub conversion: Conversion,
+    pub quality: u8,
+    /// Images wider than this are downscaled (aspect ratio preserved) before encoding.
+    pub max_width: Option<u32>,
+    /// Images taller than this are downscaled (aspect ratio preserved) before encoding.
+    pub max_height: Option<u32>,
+}
+
+impl Default for OptimizerConfig {
+    fn default() -> Self {
+        Self {
+            conversion: Conversion::Keep,
+            quality: DEFAULT_QUALITY,
+            max_width: None,
+            max_height: None,
+        }
+    }
+}
+
+/// Lowest quality accepted from the frontend. The JPEG/WebP/AVIF encoders
+/// expect roughly a 1-100 range; `JpegEncoder` in particular misbehaves at 0.
+const MIN_QUALITY: u8 = 1;
+/// Highest quality accepted from the frontend.
+const MAX_QUALITY: u8 = 100;
+
+impl OptimizerConfig {
+    /// Clamps `quality` into the range the encoders expect and drops a zero
+    /// `max_width`/`max_height`, so a bad value from the frontend can't panic
+    /// an encoder or make [`downscale`] divide by zero. Called before a
+    /// profile is accepted and persisted.
+    pub fn sanitized(mut self) -> Self {
+        self.quality = self.quality.clamp(MIN_QUALITY, MAX_QUALITY);
+        self.max_width = self.max_width.filter(|&w| w > 0);
+        self.max_height = self.max_height.filter(|&h| h > 0);
+        self
+    }
+}
+
+/// Byte counts for a single optimization pass, so the UI can report savings.
+#[derive(Debug, Clone, Copy)]
+pub struct OptimizeReport {
+    pub original_size: usize,
+    pub optimized_size: usize,
+}
+
+impl OptimizeReport {
+    pub fn bytes_saved(&self) -> i64 {
+        self.original_size as i64 - self.optimized_size as i64
+    }
+}
+
+/// A decoded RGBA image, kept around for the clipboard history.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageSnapshot {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+/// What happened when [`optimize_clipboard_image`] looked at the clipboard.
+pub enum OptimizeOutcome {
+    /// The clipboard image's hash matched `skip_if_hash`, i.e. it's the image
+    /// we ourselves wrote on a previous pass. Nothing was re-encoded or re-written.
+    SelfWrite,
+    /// Re-encoding didn't shrink the image, so the clipboard was left untouched.
+    NotSmaller { report: OptimizeReport },
+    /// The re-encoded image was smaller and has been written back. `written_hash`
+    /// is the hash callers should pass as `skip_if_hash` next time to recognize it.
+    Optimized {
+        report: OptimizeReport,
+        original: ImageSnapshot,
+        optimized: ImageSnapshot,
+        written_hash: u64,
+    },
+}
+
+/// Writes a decoded RGBA image straight to the clipboard, e.g. to restore a
+/// history entry. Returns the hash callers should treat as self-initiated.
+pub fn write_to_clipboard(snapshot: &ImageSnapshot) -> Result<u64, String> {
+    let ctx = ClipboardContext::new().map_err(|e| e.to_string())?;
+    let image = RgbaImage::from_raw(
+        snapshot.width as u32,
+        snapshot.height as u32,
+        snapshot.rgba.clone(),
+    )
+    .ok_or("history snapshot bytes don't match its reported dimensions")?;
+    ctx.set_image(RustImageData::from_dynamic_image(DynamicImage::ImageRgba8(
+        image,
+    )))
+    .map_err(|e| e.to_string())?;
+    Ok(hash_rgba(&snapshot.rgba))
+}
+
+/// Hashes the raw RGBA bytes clipboard image data is made of, so a caller can
+/// recognize a clipboard change it caused itself.
+pub fn hash_rgba(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Reads the image currently on the clipboard and, unless it's the image we
+/// wrote back ourselves last time (`skip_if_hash`), re-encodes it per `config`
+/// and writes the result back if that's actually smaller.
+///
+/// Takes the same already-opened `ctx` the caller just ran [`should_optimize`]
+/// against, so the format check and this read are guaranteed to see the same
+/// clipboard content instead of two independently opened handles.
+pub fn optimize_clipboard_image(
+    ctx: &ClipboardContext,
+    config: OptimizerConfig,
+    skip_if_hash: Option<u64>,
+) -> Result<OptimizeOutcome, String> {
+    let image_data = ctx.get_image().map_err(|e| e.to_string())?;
+    let rgba_image = image_data
+        .get_dynamic_image()
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    let (width, height) = rgba_image.dimensions();
+    let raw_bytes = rgba_image.into_raw();
+    let original_size = raw_bytes.len();
+
+    if skip_if_hash == Some(hash_rgba(&raw_bytes)) {
+        return Ok(OptimizeOutcome::SelfWrite);
+    }
+
+    let original = ImageSnapshot {
+        width: width as usize,
+        height: height as usize,
+        rgba: raw_bytes.clone(),
+    };
+    let rgba = RgbaImage::from_raw(width, height, raw_bytes)
+        .ok_or("clipboard image bytes don't match its reported dimensions")?;
+    let working = downscale(&rgba, config.max_width, config.max_height);
+
+    let encoded = encode(&working, config.conversion, config.quality)?;
+
+    // `encoded` is the actual payload a format-aware consumer gets (we publish
+    // it as-is below via `set_buffer`), so it's what "smaller" has to mean —
+    // comparing against a re-inflated RGBA buffer would hide every byte the
+    // codec/quality choice actually saved.
+    let optimized_size = encoded.len();
+    let report = OptimizeReport {
+        original_size,
+        optimized_size,
+    };
+
+    if optimized_size >= original_size {
+        return Ok(OptimizeOutcome::NotSmaller { report });
+    }
+
+    // Publish the real encoded bytes under their format's MIME type, so a
+    // consumer that understands it (e.g. a browser paste target) gets the
+    // actual smaller payload instead of a re-inflated RGBA buffer.
+    ctx.set_buffer(mime_type(config.conversion), encoded.clone())
+        .map_err(|e| e.to_string())?;
+
+    // Most paste targets only know the generic raw-pixel image format, so we
+    // still publish that representation too — decoded back from `encoded`,
+    // so its pixels are exactly what the encoded payload carries, lossy
+    // artifacts included.
+    // `written_hash` is what the self-write guard matches against next time.
+    let written = image::load_from_memory(&encoded)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    let (written_width, written_height) = written.dimensions();
+    let written_bytes = written.into_raw();
+    let written_hash = hash_rgba(&written_bytes);
+    let written_image = RgbaImage::from_raw(written_width, written_height, written_bytes.clone())
+        .ok_or("re-encoded image bytes don't match its dimensions")?;
+    ctx.set_image(RustImageData::from_dynamic_image(DynamicImage::ImageRgba8(
+        written_image,
+    )))
+    .map_err(|e| e.to_string())?;
+
+    Ok(OptimizeOutcome::Optimized {
+        report,
+        original,
+        optimized: ImageSnapshot {
+            width: written_width as usize,
+            height: written_height as usize,
+            rgba: written_bytes,
+        },
+        written_hash,
+    })
+}
+
+/// MIME type [`set_buffer`](clipboard_rs::Clipboard::set_buffer) should
+/// publish `encoded` bytes under for a given [`Conversion`].
+fn mime_type(conversion: Conversion) -> &'static str {
+    match conversion {
+        Conversion::Keep => "image/png",
+        Conversion::Jpeg => "image/jpeg",
+        Conversion::WebP => "image/webp",
+        Conversion::Avif => "image/avif",
+    }
+}
+
+/// Downscales `rgba` to fit within `max_width`/`max_height`, preserving aspect
+/// ratio. Returns the image unchanged if it already fits or no limit is set.
+fn downscale(rgba: &RgbaImage, max_width: Option<u32>, max_height: Option<u32>) -> RgbaImage {
+    let (width, height) = rgba.dimensions();
+    let scale = [
+        max_width.map(|max| max as f64 / width as f64),
+        max_height.map(|max| max as f64 / height as f64),
+    ]
+    .into_iter()
+    .flatten()
+    .fold(1.0_f64, f64::min);
+
+    if scale >= 1.0 {
+        return rgba.clone();
+    }
+
+    let new_width = ((width as f64 * scale).round() as u32).max(1);
+    let new_height = ((height as f64 * scale).round() as u32).max(1);
+    image::imageops::resize(
+        rgba,
+        new_width,
+        new_height,
+        image::imageops::FilterType::Lanczos3,
+    )
+}
+
+/// Encodes an RGBA buffer with the codec and quality `conversion`/`quality` select.
+fn encode(rgba: &RgbaImage, conversion: Conversion, quality: u8) -> Result<Vec<u8>, String> {
+    match conversion {
+        Conversion::Keep => encode_png(rgba, quality),
+        Conversion::Jpeg => encode_jpeg(rgba, quality),
+        Conversion::WebP => encode_webp(rgba, quality),
+        Conversion::Avif => encode_avif(rgba, quality),
+    }
+}
+
+/// Re-encodes an RGBA buffer as PNG, picking a compression effort from `quality`.
+fn encode_png(rgba: &RgbaImage, quality: u8) -> Result<Vec<u8>, String> {
+    let compression = if quality >= 70 {
+        CompressionType::Fast
+    } else if quality >= 30 {
+        CompressionType::Default
+    } else {
+        CompressionType::Best
+    };
+
+    let mut buf = Vec::new();
+    PngEncoder::new_with_quality(Cursor::new(&mut buf), compression, FilterType::Adaptive)
+        .write_image(
+            rgba,
+            rgba.width(),
+            rgba.height(),
+            image::ColorType::Rgba8.into(),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+/// Re-encodes an RGBA buffer as a lossy JPEG. JPEG has no alpha channel, so
+/// alpha is dropped and each pixel keeps its stored RGB color.
+fn encode_jpeg(rgba: &RgbaImage, quality: u8) -> Result<Vec<u8>, String> {
+    let rgb = DynamicImage::ImageRgba8(rgba.clone()).to_rgb8();
+    let mut buf = Vec::new();
+    JpegEncoder::new_with_quality(&mut buf, quality)
+        .encode_image(&rgb)
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+/// Re-encodes an RGBA buffer as lossy WebP at `quality` (0-100).
+fn encode_webp(rgba: &RgbaImage, quality: u8) -> Result<Vec<u8>, String> {
+    let encoder = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height());
+    Ok(encoder.encode(quality as f32).to_vec())
+}
+
+/// Re-encodes an RGBA buffer as lossy AVIF at `quality` (0-100).
+fn encode_avif(rgba: &RgbaImage, quality: u8) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+    AvifEncoder::new_with_speed_quality(&mut buf, 6, quality)
+        .write_image(
+            rgba,
+            rgba.width(),
+            rgba.height(),
+            image::ColorType::Rgba8.into(),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, image::Rgba([1, 2, 3, 255]))
+    }
+
+    #[test]
+    fn downscale_leaves_image_within_limits_unchanged() {
+        let rgba = solid(800, 600);
+        let result = downscale(&rgba, Some(1920), Some(1080));
+        assert_eq!(result.dimensions(), (800, 600));
+    }
+
+    #[test]
+    fn downscale_leaves_image_unchanged_when_no_limit_set() {
+        let rgba = solid(4000, 3000);
+        let result = downscale(&rgba, None, None);
+        assert_eq!(result.dimensions(), (4000, 3000));
+    }
+
+    #[test]
+    fn downscale_preserves_aspect_ratio_against_the_tighter_limit() {
+        // 4000x2000 is 2:1. Capped at 1000x1000, width is the binding
+        // constraint, so height should come out to half the width.
+        let rgba = solid(4000, 2000);
+        let result = downscale(&rgba, Some(1000), Some(1000));
+        assert_eq!(result.dimensions(), (1000, 500));
+    }
+
+    #[test]
+    fn downscale_rounds_and_never_collapses_to_zero() {
+        // A height limit tight enough that the naive scaled width would
+        // round down to 0 must still clamp to at least 1px.
+        let rgba = solid(3, 1000);
+        let result = downscale(&rgba, None, Some(1));
+        let (width, height) = result.dimensions();
+        assert_eq!(height, 1);
+        assert!(width >= 1);
+    }
+
+    #[test]
+    fn sanitized_clamps_quality_into_encoder_range() {
+        let config = OptimizerConfig {
+            quality: 0,
+            ..OptimizerConfig::default()
+        }
+        .sanitized();
+        assert_eq!(config.quality, MIN_QUALITY);
+
+        let config = OptimizerConfig {
+            quality: 255,
+            ..OptimizerConfig::default()
+        }
+        .sanitized();
+        assert_eq!(config.quality, MAX_QUALITY);
+    }
+
+    #[test]
+    fn sanitized_drops_zero_dimension_limits() {
+        let config = OptimizerConfig {
+            max_width: Some(0),
+            max_height: Some(0),
+            ..OptimizerConfig::default()
+        }
+        .sanitized();
+        assert_eq!(config.max_width, None);
+        assert_eq!(config.max_height, None);
+    }
+
+    #[test]
+    fn hash_rgba_agrees_on_identical_bytes_and_differs_on_changed_ones() {
+        // This is exactly the equality the self-write guard in `Handler`
+        // relies on: a hash computed over a write we made has to match the
+        // hash computed over those same bytes read back from the clipboard,
+        // and must not match if even one byte differs.
+        let written = vec![10, 20, 30, 255, 10, 20, 30, 255];
+        let read_back = written.clone();
+        let different = vec![10, 20, 30, 255, 10, 20, 31, 255];
+
+        assert_eq!(hash_rgba(&written), hash_rgba(&read_back));
+        assert_ne!(hash_rgba(&written), hash_rgba(&different));
+    }
+}
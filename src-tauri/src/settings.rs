@@ -0,0 +1,120 @@
+//! Persists the user's optimization profile to disk so it survives restarts.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::optimizer::OptimizerConfig;
+
+const CONFIG_FILE_NAME: &str = "profile.json";
+
+fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("clipboard-image-optimizer").join(CONFIG_FILE_NAME))
+}
+
+/// Loads the persisted profile, falling back to [`OptimizerConfig::default`]
+/// if there's nothing saved yet or it can't be read. Sanitized the same way
+/// [`set_settings`](crate::set_settings) sanitizes a profile coming from the
+/// frontend, so a `profile.json` written before `sanitized()` existed (or
+/// hand-edited) can't feed a bad `quality`/size limit straight to the encoders.
+pub fn load() -> OptimizerConfig {
+    load_from(config_path())
+}
+
+fn load_from(path: Option<PathBuf>) -> OptimizerConfig {
+    path.and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str::<OptimizerConfig>(&contents).ok())
+        .map(OptimizerConfig::sanitized)
+        .unwrap_or_default()
+}
+
+/// Persists `config` as the user's active profile.
+pub fn save(config: &OptimizerConfig) -> Result<(), String> {
+    let path = config_path().ok_or("couldn't determine a config directory for this platform")?;
+    save_to(path, config)
+}
+
+fn save_to(path: PathBuf, config: &OptimizerConfig) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let contents = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::write(path, contents).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimizer::Conversion;
+
+    /// A path under the OS temp dir, not the real config dir, so these tests
+    /// don't depend on (or clobber) whatever `profile.json` a real run of the
+    /// app may have left on the machine running them.
+    fn temp_profile_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("clipboard-image-optimizer-test-{name}.json"))
+    }
+
+    #[test]
+    fn config_round_trips_through_json_like_the_persisted_file_does() {
+        let config = OptimizerConfig {
+            conversion: Conversion::WebP,
+            quality: 42,
+            max_width: Some(1920),
+            max_height: None,
+        };
+
+        let contents = serde_json::to_string_pretty(&config).unwrap();
+        let restored: OptimizerConfig = serde_json::from_str(&contents).unwrap();
+
+        assert_eq!(restored.conversion, config.conversion);
+        assert_eq!(restored.quality, config.quality);
+        assert_eq!(restored.max_width, config.max_width);
+        assert_eq!(restored.max_height, config.max_height);
+    }
+
+    #[test]
+    fn load_falls_back_to_defaults_when_nothing_is_persisted() {
+        let path = temp_profile_path("missing");
+        let _ = fs::remove_file(&path);
+
+        let config = load_from(Some(path));
+        assert_eq!(config.quality, OptimizerConfig::default().quality);
+    }
+
+    #[test]
+    fn save_then_load_round_trips_through_a_real_file() {
+        let path = temp_profile_path("round-trip");
+        let config = OptimizerConfig {
+            conversion: Conversion::Avif,
+            quality: 55,
+            max_width: Some(1280),
+            max_height: Some(720),
+        };
+
+        save_to(path.clone(), &config).unwrap();
+        let loaded = load_from(Some(path.clone()));
+
+        assert_eq!(loaded.conversion, config.conversion);
+        assert_eq!(loaded.quality, config.quality);
+        assert_eq!(loaded.max_width, config.max_width);
+        assert_eq!(loaded.max_height, config.max_height);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_sanitizes_a_profile_persisted_before_sanitized_existed() {
+        let path = temp_profile_path("unsanitized");
+        fs::write(
+            &path,
+            r#"{"conversion":"jpeg","quality":0,"max_width":0,"max_height":null}"#,
+        )
+        .unwrap();
+
+        let config = load_from(Some(path.clone()));
+        // Matches optimizer::MIN_QUALITY; `sanitized()` owns the real bound.
+        assert_eq!(config.quality, 1);
+        assert_eq!(config.max_width, None);
+
+        let _ = fs::remove_file(&path);
+    }
+}